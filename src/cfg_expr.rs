@@ -0,0 +1,179 @@
+//! A small boolean model of `cfg` predicates, used to detect stacked
+//! `#[cfg_vis]` attributes whose predicates could be true at the same time.
+
+/// A boolean expression over `cfg` predicates.
+///
+/// This mirrors the subset of `#[cfg(...)]` syntax that can appear inside
+/// `#[cfg_vis($cfg, $vis)]`: atomic flags/key-values, `not`, `any` and `all`.
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Cfg {
+    True,
+    False,
+    Cfg(syn::Ident, Option<String>),
+    Not(Box<Cfg>),
+    Any(Vec<Cfg>),
+    All(Vec<Cfg>),
+}
+
+/// `cfg` keys whose values are mutually exclusive: a given compilation can
+/// only ever match one value of each of these keys at a time. `unix` and
+/// `windows` are also folded into `target_family` by [`canonical_family`],
+/// since they're shorthand for `target_family = "unix"`/`"windows"`.
+const MUTUALLY_EXCLUSIVE_FAMILIES: &[&str] =
+    &["target_os", "target_arch", "target_family", "target_endian"];
+
+impl Cfg {
+    pub(crate) fn parse(nested: &syn::NestedMeta) -> Cfg {
+        match nested {
+            syn::NestedMeta::Lit(syn::Lit::Bool(b)) => {
+                if b.value {
+                    Cfg::True
+                } else {
+                    Cfg::False
+                }
+            }
+            syn::NestedMeta::Lit(_) => Cfg::True,
+            syn::NestedMeta::Meta(meta) => Self::parse_meta(meta),
+        }
+    }
+
+    fn parse_meta(meta: &syn::Meta) -> Cfg {
+        match meta {
+            syn::Meta::Path(path) => match path.get_ident() {
+                Some(ident) => Cfg::Cfg(ident.clone(), None),
+                None => Cfg::True,
+            },
+            syn::Meta::NameValue(nv) => match nv.path.get_ident() {
+                Some(ident) => {
+                    let value = match &nv.lit {
+                        syn::Lit::Str(s) => Some(s.value()),
+                        _ => None,
+                    };
+                    Cfg::Cfg(ident.clone(), value)
+                }
+                None => Cfg::True,
+            },
+            syn::Meta::List(list) => {
+                let nested: Vec<Cfg> = list.nested.iter().map(Cfg::parse).collect();
+                match list.path.get_ident().map(|ident| ident.to_string()) {
+                    Some(ref s) if s == "any" => Cfg::Any(nested),
+                    Some(ref s) if s == "all" => Cfg::All(nested),
+                    Some(ref s) if s == "not" => {
+                        Cfg::Not(Box::new(nested.into_iter().next().unwrap_or(Cfg::True)))
+                    }
+                    // an unrecognized predicate combinator is treated as an
+                    // opaque atomic variable, keyed by its own name
+                    Some(_) => list
+                        .path
+                        .get_ident()
+                        .map(|ident| Cfg::Cfg(ident.clone(), None))
+                        .unwrap_or(Cfg::True),
+                    None => Cfg::True,
+                }
+            }
+        }
+    }
+
+    fn atoms(&self, out: &mut Vec<(String, Option<String>)>) {
+        match self {
+            Cfg::True | Cfg::False => {}
+            Cfg::Cfg(name, value) => out.push((name.to_string(), value.clone())),
+            Cfg::Not(inner) => inner.atoms(out),
+            Cfg::Any(list) | Cfg::All(list) => list.iter().for_each(|c| c.atoms(out)),
+        }
+    }
+
+    fn eval(&self, env: &dyn Fn(&str, Option<&str>) -> bool) -> bool {
+        match self {
+            Cfg::True => true,
+            Cfg::False => false,
+            Cfg::Cfg(name, value) => env(&name.to_string(), value.as_deref()),
+            Cfg::Not(inner) => !inner.eval(env),
+            Cfg::Any(list) => list.iter().any(|c| c.eval(env)),
+            Cfg::All(list) => list.iter().all(|c| c.eval(env)),
+        }
+    }
+}
+
+/// Whether `All([a, b])` is satisfiable, i.e. whether there is some
+/// compilation under which both `a` and `b` hold at once.
+///
+/// This brute-forces every truth assignment of the atomic variables that
+/// appear in `a` or `b`, skipping assignments that would set two different
+/// values of the same mutually-exclusive family (e.g. `target_os = "linux"`
+/// and `target_os = "windows"`) to true at the same time.
+pub(crate) fn overlaps(a: &Cfg, b: &Cfg) -> bool {
+    let mut atoms = Vec::new();
+    a.atoms(&mut atoms);
+    b.atoms(&mut atoms);
+    atoms.sort();
+    atoms.dedup();
+
+    // guard against pathological blow-up; no realistic `cfg_vis` predicate
+    // comes close to this many distinct atoms
+    if atoms.len() > 20 {
+        return true;
+    }
+
+    let n = atoms.len();
+    for mask in 0..(1u32 << n) {
+        let assignment: Vec<bool> = (0..n).map(|i| (mask >> i) & 1 == 1).collect();
+        if !respects_mutual_exclusion(&atoms, &assignment) {
+            continue;
+        }
+
+        let env = |name: &str, value: Option<&str>| {
+            atoms
+                .iter()
+                .position(|(n, v)| n == name && v.as_deref() == value)
+                .map(|i| assignment[i])
+                .unwrap_or(false)
+        };
+
+        if a.eval(&env) && b.eval(&env) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn respects_mutual_exclusion(atoms: &[(String, Option<String>)], assignment: &[bool]) -> bool {
+    let mut true_values: Vec<(&str, &str)> = Vec::new();
+
+    for (i, (name, value)) in atoms.iter().enumerate() {
+        if !assignment[i] {
+            continue;
+        }
+
+        let Some((family, value)) = canonical_family(name, value.as_deref()) else {
+            continue;
+        };
+
+        if true_values.iter().any(|&(f, v)| f == family && v != value) {
+            return false;
+        }
+        if !true_values.contains(&(family, value)) {
+            true_values.push((family, value));
+        }
+    }
+
+    true
+}
+
+/// Maps an atomic `cfg` (name, value) to its mutually-exclusive family and
+/// the value it asserts within that family, if any. `unix` and `windows` are
+/// shorthand for `target_family = "unix"` and `target_family = "windows"`,
+/// so they're folded into the same `target_family` group as the spelled-out
+/// form, which is what makes stacking `#[cfg_vis(unix, ..)]` with
+/// `#[cfg_vis(windows, ..)]` correctly non-overlapping.
+fn canonical_family<'a>(name: &'a str, value: Option<&'a str>) -> Option<(&'a str, &'a str)> {
+    match (name, value) {
+        ("unix", None) => Some(("target_family", "unix")),
+        ("windows", None) => Some(("target_family", "windows")),
+        (family, Some(value)) if MUTUALLY_EXCLUSIVE_FAMILIES.contains(&family) => {
+            Some((family, value))
+        }
+        _ => None,
+    }
+}
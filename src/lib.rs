@@ -1,14 +1,14 @@
 #![doc = include_str!("../README.md")]
 
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::Span;
 use quote::quote;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{parenthesized, parse_macro_input, parse_quote};
 
+mod cfg_expr;
+
 struct CfgVisAttrArgs {
     cfg: syn::NestedMeta,
     vis: syn::Visibility,
@@ -54,13 +54,25 @@ impl Parse for CfgVisAttrArgsWithParens {
 /// will expend to
 ///
 /// ```ignore
-/// #[cfg($cfg)]
+/// #[cfg(any($cfg, docsrs))]
+/// #[cfg_attr(docsrs, doc(cfg($cfg)))]
 /// $vis $($item)*
 ///
-/// #[cfg(not($cfg))]
+/// #[cfg(all(not($cfg), not(docsrs)))]
 /// $default_vis $($item)*
 /// ```
 ///
+/// `docsrs` is the conventional cfg that docs.rs (and
+/// `cargo +nightly doc --cfg docsrs`) sets during a documentation build;
+/// plain `cfg(doc)` is set by rustdoc on *every* channel, including stable,
+/// while `#[doc(cfg(...))]` itself is still gated behind the unstable
+/// `doc_cfg` feature, so gating on bare `doc` would break `cargo doc` on
+/// stable for any crate using `cfg_vis`. Crates that want the rendered
+/// `#[doc(cfg($cfg))]` note need to enable that feature themselves under the
+/// same cfg, e.g. with `#![cfg_attr(docsrs, feature(doc_cfg))]` in their
+/// crate root (the same opt-in the standard library's own nightly docs rely
+/// on); without it, a `docsrs` build fails on that feature gate.
+///
 /// ## Example
 ///
 /// ```rust
@@ -89,7 +101,7 @@ fn cfg_vis_impl(
 ) -> syn::Result<proc_macro::TokenStream> {
     let default_item = item.clone();
 
-    let (default_vis, _) = match &mut item {
+    let (default_vis, attrs) = match &mut item {
         syn::Item::Const(i) => (&mut i.vis, &i.attrs),
         syn::Item::Enum(i) => (&mut i.vis, &i.attrs),
         syn::Item::ExternCrate(i) => (&mut i.vis, &i.attrs),
@@ -111,91 +123,49 @@ fn cfg_vis_impl(
         }
     };
 
-    *default_vis = vis;
+    guard_cfg_vis_no_overlap(&cfg, attrs)?;
 
-    let check_unique = assert_cfg_vis_is_unique(&item);
+    *default_vis = vis;
 
     let tokens = quote! {
-        #[cfg(#cfg)]
+        #[cfg(any(#cfg, docsrs))]
+        #[cfg_attr(docsrs, doc(cfg(#cfg)))]
         #item
 
-        #[cfg(not(#cfg))]
+        #[cfg(all(not(#cfg), not(docsrs)))]
         #default_item
-
-        #check_unique
     };
 
     Ok(proc_macro::TokenStream::from(tokens))
 }
 
-fn assert_cfg_vis_is_unique(item: &syn::Item) -> TokenStream {
-    let mut hasher = DefaultHasher::new();
-
-    PartialHashItemHelper(item).hash(&mut hasher);
-
-    // different version of package make a different accumulator
-    env!("CARGO_PKG_VERSION").hash(&mut hasher);
-
-    let name = format!("__CFG_VIS_MUST_CALL_ONCE_{}", hasher.finish());
-    let check_unique = syn::Ident::new(&name, Span::call_site());
-
-    quote! {
-        const #check_unique: () = ();
-    }
-}
+/// Checks a just-applied `#[cfg_vis($cfg, ..)]` predicate against any
+/// `#[cfg_vis]` attributes still stacked below it on the same item, erroring
+/// out if two such predicates could be true for the same compilation (e.g.
+/// `target_os = "linux"` stacked with `target_os = "windows"` would be fine,
+/// but stacking it with `unix` would not).
+fn guard_cfg_vis_no_overlap(cfg: &syn::NestedMeta, attrs: &[syn::Attribute]) -> syn::Result<()> {
+    let this = cfg_expr::Cfg::parse(cfg);
+
+    for attr in attrs {
+        if !is_cfg_vis(attr) {
+            continue;
+        }
 
-struct PartialHashItemHelper<'a>(&'a syn::Item);
+        let tokens = &attr.tokens;
+        let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg: other, .. }) = parse_quote!(#tokens);
+        let other = cfg_expr::Cfg::parse(&other);
 
-impl Hash for PartialHashItemHelper<'_> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        std::mem::discriminant(self.0).hash(state);
-        match &self.0 {
-            syn::Item::Const(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Enum(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::ExternCrate(v0) => {
-                v0.ident.hash(state);
-                v0.rename.hash(state);
-            }
-            syn::Item::Fn(v0) => {
-                v0.sig.ident.hash(state);
-            }
-            syn::Item::Macro(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Macro2(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Mod(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Static(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Struct(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Trait(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::TraitAlias(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Type(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Union(v0) => {
-                v0.ident.hash(state);
-            }
-            syn::Item::Use(v0) => {
-                v0.tree.hash(state);
-            }
-            _ => self.0.hash(state),
+        if cfg_expr::overlaps(&this, &other) {
+            return Err(syn::Error::new(
+                attr.span(),
+                "stacked `cfg_vis` predicates overlap: both branches could be active \
+                 for the same compilation",
+            ));
         }
     }
+
+    Ok(())
 }
 
 ///
@@ -216,6 +186,43 @@ impl Hash for PartialHashItemHelper<'_> {
 /// }
 /// ```
 ///
+/// Stacking several `#[cfg_vis]` attributes on one field works the same way
+/// it does on items: each branch gets its own visibility and its own
+/// `#[cfg_attr(docsrs, doc(cfg(...)))]`. Unlike the single-predicate case, the
+/// branches are gated on their plain `$cfg`, so a `docsrs` build renders only
+/// the branch whose predicate holds for the target being documented, or the
+/// unannotated default-visibility field when none does.
+///
+/// ```rust
+/// use cfg_vis::cfg_vis_fields;
+///
+/// #[cfg_vis_fields]
+/// struct Foo {
+///     #[cfg_vis(target_os = "linux", pub)]
+///     #[cfg_vis(target_os = "windows", pub(super))]
+///     foo: i32,
+/// }
+/// ```
+///
+/// `#[cfg_vis_fields]` also applies to `enum`s, walking every variant's
+/// fields. Since variant fields have no visibility of their own, the `$vis`
+/// in `#[cfg_vis($cfg, $vis)]` is ignored there and the attribute instead
+/// becomes a plain `#[cfg($cfg)]` on the field, conditionally including or
+/// excluding it per target:
+///
+/// ```rust
+/// use cfg_vis::cfg_vis_fields;
+///
+/// #[cfg_vis_fields]
+/// enum Bar {
+///     Baz {
+///         // only present while the target is linux.
+///         #[cfg_vis(target_os = "linux", pub)]
+///         foo: i32,
+///     },
+/// }
+/// ```
+///
 #[proc_macro_attribute]
 pub fn cfg_vis_fields(
     attr: proc_macro::TokenStream,
@@ -239,6 +246,22 @@ pub fn cfg_vis_fields(
 }
 
 fn cfg_vis_fields_impl(mut item: syn::Item) -> syn::Result<syn::Item> {
+    if let syn::Item::Enum(e) = &mut item {
+        for variant in &mut e.variants {
+            match &mut variant.fields {
+                syn::Fields::Named(f) => {
+                    f.named = find_replace_cfg_vis_presence(std::mem::take(&mut f.named))?;
+                }
+                syn::Fields::Unnamed(f) => {
+                    f.unnamed = find_replace_cfg_vis_presence(std::mem::take(&mut f.unnamed))?;
+                }
+                syn::Fields::Unit => {}
+            }
+        }
+
+        return Ok(item);
+    }
+
     let fields = match &mut item {
         syn::Item::Struct(s) => match &mut s.fields {
             syn::Fields::Named(f) => &mut f.named,
@@ -252,7 +275,7 @@ fn cfg_vis_fields_impl(mut item: syn::Item) -> syn::Result<syn::Item> {
         _ => {
             return Err(syn::Error::new(
                 item.span(),
-                "`cfg_vis_fields` can only apply on struct or union",
+                "`cfg_vis_fields` can only apply on struct, union or enum",
             ))
         }
     };
@@ -262,51 +285,163 @@ fn cfg_vis_fields_impl(mut item: syn::Item) -> syn::Result<syn::Item> {
     Ok(item)
 }
 
-fn find_replace_cfg_vis(
+/// Like [`find_replace_cfg_vis`], but for fields that have no visibility of
+/// their own (enum variant fields): every `#[cfg_vis($cfg, ..)]` on a field
+/// is replaced by a single `#[cfg(any($cfg_1, $cfg_2, ..))]`, so the
+/// attribute(s) only toggle whether the field is present for a given target
+/// (included as soon as any one predicate holds), discarding the requested
+/// `$vis`.
+fn find_replace_cfg_vis_presence(
     fields: Punctuated<syn::Field, syn::Token![,]>,
 ) -> syn::Result<Punctuated<syn::Field, syn::Token![,]>> {
     let mut fields_replaced = Punctuated::new();
     for mut field in fields {
-        if let Some(pos) = guard_cfg_vis_unique(&field.attrs)? {
-            let attr = &field.attrs[pos].tokens;
-            let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg, vis }) = parse_quote!(#attr);
-
-            let mut field_replaced = field.clone();
-            field_replaced.attrs[pos] = parse_quote! { #[cfg(#cfg)] };
-            field_replaced.vis = vis;
-            fields_replaced.push(field_replaced);
+        let positions = cfg_vis_positions(&field.attrs);
+        guard_no_overlapping_field_cfgs(&field.attrs, &positions)?;
+
+        let cfgs: Vec<syn::NestedMeta> = positions
+            .iter()
+            .map(|&pos| {
+                let attr = &field.attrs[pos].tokens;
+                let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg, .. }) = parse_quote!(#attr);
+                cfg
+            })
+            .collect();
+
+        for &pos in positions.iter().skip(1).rev() {
+            field.attrs.remove(pos);
+        }
 
-            field.attrs[pos] = parse_quote! { #[cfg(not(#cfg))] };
+        if let [cfg] = &cfgs[..] {
+            field.attrs[positions[0]] = parse_quote! { #[cfg(#cfg)] };
+        } else if !cfgs.is_empty() {
+            field.attrs[positions[0]] = parse_quote! { #[cfg(any(#(#cfgs),*))] };
         }
+
         fields_replaced.push(field);
     }
 
     Ok(fields_replaced)
 }
 
-fn guard_cfg_vis_unique(attrs: &[syn::Attribute]) -> syn::Result<Option<usize>> {
-    let mut count = 0;
-    let mut pos = None;
+fn find_replace_cfg_vis(
+    fields: Punctuated<syn::Field, syn::Token![,]>,
+) -> syn::Result<Punctuated<syn::Field, syn::Token![,]>> {
+    let mut fields_replaced = Punctuated::new();
+    for field in fields {
+        fields_replaced.extend(expand_field_cfg_vis(field)?);
+    }
 
-    for (i, attr) in attrs.iter().enumerate() {
-        let has_cfg_vis = attr
-            .path
-            .get_ident()
-            .filter(|&ident| ident == "cfg_vis")
-            .is_some();
+    Ok(fields_replaced)
+}
 
-        if has_cfg_vis {
-            count += 1;
-            pos = Some(i);
-        }
+/// Expands a single field's `#[cfg_vis]` attribute(s) into one field copy per
+/// predicate (each gated on its `$cfg` and carrying its `$vis`) plus a final
+/// copy gated on none of the predicates holding, which keeps the field's own
+/// visibility.
+///
+/// Each predicate's field copy is annotated with
+/// `#[cfg_attr(docsrs, doc(cfg(#cfg)))]`; the default copy is not. With a
+/// single `#[cfg_vis]`, as with item-level `cfg_vis`, the predicate's copy is
+/// gated on `any($cfg, docsrs)` and the default copy on
+/// `all(not($cfg), not(docsrs))`, so a `docsrs` build always shows the
+/// predicate's copy. With several, each copy is gated on its plain `$cfg`,
+/// so a `docsrs` build shows only the copy whose predicate holds, if any.
+fn expand_field_cfg_vis(mut field: syn::Field) -> syn::Result<Vec<syn::Field>> {
+    let positions = cfg_vis_positions(&field.attrs);
+
+    if positions.is_empty() {
+        return Ok(vec![field]);
+    }
 
-        if count > 1 {
-            return Err(syn::Error::new(
-                attr.span(),
-                "multiple `cfg_vis` is not allowed",
-            ));
+    guard_no_overlapping_field_cfgs(&field.attrs, &positions)?;
+
+    if let [pos] = positions[..] {
+        let attr = &field.attrs[pos].tokens;
+        let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg, vis }) = parse_quote!(#attr);
+
+        let mut field_replaced = field.clone();
+        field_replaced.attrs[pos] = parse_quote! { #[cfg(any(#cfg, docsrs))] };
+        field_replaced
+            .attrs
+            .push(parse_quote! { #[cfg_attr(docsrs, doc(cfg(#cfg)))] });
+        field_replaced.vis = vis;
+
+        field.attrs[pos] = parse_quote! { #[cfg(all(not(#cfg), not(docsrs)))] };
+
+        return Ok(vec![field_replaced, field]);
+    }
+
+    let mut cfgs = Vec::with_capacity(positions.len());
+    let mut fields_replaced = Vec::with_capacity(positions.len() + 1);
+
+    for &pos in &positions {
+        let attr = &field.attrs[pos].tokens;
+        let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg, vis }) = parse_quote!(#attr);
+
+        let mut variant = field.clone();
+        variant.attrs.retain(|attr| !is_cfg_vis(attr));
+        variant.attrs.push(parse_quote! { #[cfg(#cfg)] });
+        variant
+            .attrs
+            .push(parse_quote! { #[cfg_attr(docsrs, doc(cfg(#cfg)))] });
+        variant.vis = vis;
+        fields_replaced.push(variant);
+
+        cfgs.push(cfg);
+    }
+
+    field.attrs.retain(|attr| !is_cfg_vis(attr));
+    let nots = cfgs.iter().map(|cfg| quote! { not(#cfg) });
+    field.attrs.push(parse_quote! { #[cfg(all(#(#nots),*))] });
+    fields_replaced.push(field);
+
+    Ok(fields_replaced)
+}
+
+/// Checks every pair of `#[cfg_vis]` attributes stacked on the same field,
+/// erroring out if two of their predicates could be true for the same
+/// compilation. Mirrors [`guard_cfg_vis_no_overlap`] for the item-level
+/// macro, adapted to fields, which see every stacked predicate at once
+/// instead of one attribute layer at a time.
+fn guard_no_overlapping_field_cfgs(
+    attrs: &[syn::Attribute],
+    positions: &[usize],
+) -> syn::Result<()> {
+    let mut parsed = Vec::with_capacity(positions.len());
+    for &pos in positions {
+        let tokens = &attrs[pos].tokens;
+        let CfgVisAttrArgsWithParens(CfgVisAttrArgs { cfg, .. }) = parse_quote!(#tokens);
+        parsed.push(cfg_expr::Cfg::parse(&cfg));
+    }
+
+    for i in 0..parsed.len() {
+        for j in (i + 1)..parsed.len() {
+            if cfg_expr::overlaps(&parsed[i], &parsed[j]) {
+                return Err(syn::Error::new(
+                    attrs[positions[j]].span(),
+                    "stacked `cfg_vis` predicates overlap: both branches could be active \
+                     for the same compilation",
+                ));
+            }
         }
     }
 
-    Ok(pos)
+    Ok(())
+}
+
+fn cfg_vis_positions(attrs: &[syn::Attribute]) -> Vec<usize> {
+    attrs
+        .iter()
+        .enumerate()
+        .filter(|(_, attr)| is_cfg_vis(attr))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn is_cfg_vis(attr: &syn::Attribute) -> bool {
+    attr.path
+        .get_ident()
+        .filter(|&ident| ident == "cfg_vis")
+        .is_some()
 }
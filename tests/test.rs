@@ -42,6 +42,16 @@ mod inner {
         #[cfg_vis(target_os = "windows", pub(super))]
         prv_in_macos: i32,
     }
+
+    #[cfg_vis_fields]
+    pub enum Shape {
+        Circle {
+            #[cfg_vis(unix, pub)]
+            #[cfg_vis(windows, pub(super))]
+            radius: i32,
+        },
+        Square(#[cfg_vis(test, pub)] i32),
+    }
 }
 
 // mod will_not_compile {
@@ -56,12 +66,17 @@ mod inner {
 //         bar.1;
 //     }
 //
+//     // `test` and `target_os = "windows"` are not mutually exclusive (you can
+//     // run `cargo test` on Windows), so the overlap checker rejects this
+//     // stacking with a compile error instead of silently expanding into two
+//     // colliding definitions.
 //     #[cfg_vis(test, pub)]
 //     #[cfg_vis(target_os = "windows", pub(super))]
 //     fn dup_cfg() -> bool {
 //         true
 //     }
 //
+//     // same overlap, caught by the field-level checker
 //     #[cfg_vis_fields]
 //     struct DupAttr {
 //         #[cfg_vis(test, pub)]
@@ -112,3 +127,18 @@ fn struct_fields_work(foo: inner::Foo, bar: inner::Bar, baz: inner::Baz) {
         baz.prv_in_macos;
     }
 }
+
+#[cfg(test)]
+fn enum_fields_work(shape: inner::Shape) {
+    match shape {
+        #[cfg(any(unix, windows))]
+        inner::Shape::Circle { radius } => {
+            radius;
+        }
+        inner::Shape::Square(side) => {
+            side;
+        }
+        #[cfg(not(any(unix, windows)))]
+        inner::Shape::Circle {} => {}
+    }
+}